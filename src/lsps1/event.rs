@@ -0,0 +1,96 @@
+// This file is Copyright its original authors, visible in version control
+// history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Events emitted by [`LSPS1ServiceHandler`] for the LSP operator to act on.
+//!
+//! [`LSPS1ServiceHandler`]: super::service::LSPS1ServiceHandler
+
+use super::msgs::{OrderId, OrderParams};
+use crate::lsps0::ser::RequestId;
+
+use bitcoin::secp256k1::PublicKey;
+
+/// An event emitted by [`LSPS1ServiceHandler`] that needs to be handled by the LSP operator.
+///
+/// [`LSPS1ServiceHandler`]: super::service::LSPS1ServiceHandler
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LSPS1ServiceEvent {
+	/// The client requested a new order; the LSP should price it and call
+	/// [`send_payment_details`]/[`send_payment_details_with_offer`] with the `request_id` from
+	/// this event so the response can be correlated to the client's request.
+	///
+	/// [`send_payment_details`]: super::service::LSPS1ServiceHandler::send_payment_details
+	/// [`send_payment_details_with_offer`]: super::service::LSPS1ServiceHandler::send_payment_details_with_offer
+	RequestForPaymentDetails {
+		/// The identifier of the client's `CreateOrder` request.
+		request_id: RequestId,
+		/// The client node requesting the order.
+		counterparty_node_id: PublicKey,
+		/// The requested channel parameters.
+		order: OrderParams,
+	},
+	/// The client polled for an order's status via a genuine `GetOrder` request; the LSP should
+	/// check whether the order's payment has been confirmed and call [`update_order_status`]
+	/// with the `request_id` from this event so the response can be correlated to the client's
+	/// request.
+	///
+	/// [`update_order_status`]: super::service::LSPS1ServiceHandler::update_order_status
+	CheckPaymentConfirmation {
+		/// The identifier of the client's `GetOrder` request that triggered this check.
+		request_id: RequestId,
+		/// The client node that polled for the order.
+		counterparty_node_id: PublicKey,
+		/// The order being polled for.
+		order_id: OrderId,
+	},
+	/// An on-chain order payment reached its required number of confirmations without any
+	/// client request being involved, and the order is now ready for the LSP to open the
+	/// purchased channel.
+	///
+	/// Unlike [`CheckPaymentConfirmation`], this carries no `request_id`: nothing the client
+	/// sent prompted it, so there is no client request to correlate a wire reply to. The LSP
+	/// should open the channel and call [`link_channel_to_order`]; the confirmed state is
+	/// reported to the client the next time it sends a genuine `GetOrder` request.
+	///
+	/// [`CheckPaymentConfirmation`]: Self::CheckPaymentConfirmation
+	/// [`link_channel_to_order`]: super::service::LSPS1ServiceHandler::link_channel_to_order
+	OrderPaymentConfirmed {
+		/// The client node the order belongs to.
+		counterparty_node_id: PublicKey,
+		/// The order whose payment was just confirmed.
+		order_id: OrderId,
+	},
+	/// A client's order was found to have an invalid state while handling a genuine `GetOrder`
+	/// request; the LSP should return any partial payment it received for the order. Orders that
+	/// expire unpaid are not reported through this event but through [`OrderExpired`].
+	///
+	/// [`OrderExpired`]: Self::OrderExpired
+	Refund {
+		/// The identifier of the client request that surfaced the need for a refund.
+		request_id: RequestId,
+		/// The client node the order belongs to.
+		counterparty_node_id: PublicKey,
+		/// The order being refunded.
+		order_id: OrderId,
+	},
+	/// An order was created but never paid and has passed its expiry, found by a periodic
+	/// background sweep rather than any client request.
+	///
+	/// Unlike [`Refund`], this carries no `request_id`: the expiry sweep isn't triggered by a
+	/// client request, so there is no wire response to correlate it to. The LSP should return
+	/// any partial on-chain payment it received for the order out-of-band.
+	///
+	/// [`Refund`]: Self::Refund
+	OrderExpired {
+		/// The client node the order belonged to.
+		counterparty_node_id: PublicKey,
+		/// The order that expired unpaid.
+		order_id: OrderId,
+	},
+}
@@ -0,0 +1,271 @@
+// This file is Copyright its original authors, visible in version control
+// history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Message, request, and response types for the LSPS1 channel request protocol.
+
+use crate::lsps0::ser::RequestId;
+use crate::prelude::{String, Vec};
+
+use lightning::chain::transaction::OutPoint;
+use lightning::ln::msgs::DecodeError;
+use lightning::util::ser::{Readable, Writeable, Writer};
+use lightning::io;
+
+use chrono::{DateTime, Utc};
+
+use serde::{Deserialize, Serialize};
+
+/// The error code returned when a client's [`CreateOrderRequest`] does not match any of the
+/// options the LSP advertises via [`OptionsSupported`].
+pub const LSPS1_CREATE_ORDER_REQUEST_ORDER_MISMATCH_ERROR_CODE: i32 = 105;
+
+/// The identifier of an order, generated by the LSP when it is created.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OrderId(pub String);
+
+impl Writeable for OrderId {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), io::Error> {
+		self.0.write(writer)
+	}
+}
+
+impl Readable for OrderId {
+	fn read<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		Ok(OrderId(Readable::read(reader)?))
+	}
+}
+
+/// The channel parameters requested by the client as part of a [`CreateOrderRequest`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct OrderParams {
+	/// The requested channel size, denominated in satoshis, that the LSP will provide.
+	pub lsp_balance_sat: u64,
+	/// The client's initial balance in the channel, denominated in satoshis.
+	pub client_balance_sat: u64,
+	/// The number of confirmations the funding transaction requires before the channel is
+	/// usable.
+	pub required_channel_confirmations: u16,
+	/// The number of blocks within which the funding transaction is expected to confirm.
+	pub funding_confirms_within_blocks: u16,
+	/// The number of blocks after which the channel will be closed automatically.
+	pub channel_expiry_blocks: u32,
+	/// A token supplied by the client, echoing [`LSPS1ServiceConfig::token`] if the LSP
+	/// requires one.
+	///
+	/// [`LSPS1ServiceConfig::token`]: super::service::LSPS1ServiceConfig::token
+	pub token: Option<String>,
+	/// An on-chain address the LSP should refund to if the order is never fulfilled.
+	pub refund_onchain_address: Option<String>,
+	/// Whether the client wants the channel publicly announced.
+	pub announce_channel: bool,
+}
+
+impl Writeable for OrderParams {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), io::Error> {
+		self.lsp_balance_sat.write(writer)?;
+		self.client_balance_sat.write(writer)?;
+		self.required_channel_confirmations.write(writer)?;
+		self.funding_confirms_within_blocks.write(writer)?;
+		self.channel_expiry_blocks.write(writer)?;
+		self.token.write(writer)?;
+		self.refund_onchain_address.write(writer)?;
+		self.announce_channel.write(writer)?;
+		Ok(())
+	}
+}
+
+impl Readable for OrderParams {
+	fn read<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		Ok(OrderParams {
+			lsp_balance_sat: Readable::read(reader)?,
+			client_balance_sat: Readable::read(reader)?,
+			required_channel_confirmations: Readable::read(reader)?,
+			funding_confirms_within_blocks: Readable::read(reader)?,
+			channel_expiry_blocks: Readable::read(reader)?,
+			token: Readable::read(reader)?,
+			refund_onchain_address: Readable::read(reader)?,
+			announce_channel: Readable::read(reader)?,
+		})
+	}
+}
+
+/// The payment instructions the LSP hands back to the client in response to a
+/// [`CreateOrderRequest`], along with the LSP's own bookkeeping for the amount owed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct OrderPayment {
+	/// The total price of the order, denominated in satoshis.
+	pub order_total_sat: u64,
+	/// The fee portion of [`order_total_sat`] charged by the LSP.
+	///
+	/// [`order_total_sat`]: Self::order_total_sat
+	pub fee_total_sat: u64,
+	/// A BOLT11 invoice the client can pay for the order, if the LSP offers bolt11 payment.
+	pub bolt11_invoice: Option<String>,
+	/// An on-chain address the client can pay for the order, if the LSP offers on-chain
+	/// payment.
+	pub onchain_address: Option<String>,
+	/// The number of confirmations an on-chain payment requires before it is considered paid.
+	pub minimum_confirmations: u32,
+	/// A reusable BOLT12 offer the client can pay for the order, if the LSP offers BOLT12
+	/// payment.
+	///
+	/// Minted by [`LSPS1ServiceHandler::send_payment_details_with_offer`] and advertised here
+	/// so clients paying via BOLT12 can request an invoice for it.
+	///
+	/// [`LSPS1ServiceHandler::send_payment_details_with_offer`]: super::service::LSPS1ServiceHandler::send_payment_details_with_offer
+	pub bolt12_offer: Option<String>,
+}
+
+impl Writeable for OrderPayment {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), io::Error> {
+		self.order_total_sat.write(writer)?;
+		self.fee_total_sat.write(writer)?;
+		self.bolt11_invoice.write(writer)?;
+		self.onchain_address.write(writer)?;
+		self.minimum_confirmations.write(writer)?;
+		self.bolt12_offer.write(writer)?;
+		Ok(())
+	}
+}
+
+impl Readable for OrderPayment {
+	fn read<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		Ok(OrderPayment {
+			order_total_sat: Readable::read(reader)?,
+			fee_total_sat: Readable::read(reader)?,
+			bolt11_invoice: Readable::read(reader)?,
+			onchain_address: Readable::read(reader)?,
+			minimum_confirmations: Readable::read(reader)?,
+			bolt12_offer: Readable::read(reader)?,
+		})
+	}
+}
+
+/// The real channel details reported once the LSP has opened the channel purchased by an
+/// order, populated from the funding transaction the LSP actually broadcast.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ChannelInfo {
+	/// When the funding transaction was first seen confirmed.
+	pub funded_at: DateTime<Utc>,
+	/// The purchased channel's funding outpoint.
+	pub funding_outpoint: OutPoint,
+	/// When the channel will be closed automatically.
+	pub expires_at: DateTime<Utc>,
+}
+
+/// The lifecycle state of an order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderState {
+	/// The order has been created but not yet paid.
+	Created,
+	/// The order has been paid and the channel has been opened.
+	Completed,
+	/// The order was created but never paid and has passed its expiry.
+	Expired,
+}
+
+/// The channel configuration options an LSP advertises support for via [`GetInfoResponse`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct OptionsSupported {
+	/// The minimum channel size the LSP will open, denominated in satoshis.
+	pub min_channel_balance_sat: u64,
+	/// The maximum channel size the LSP will open, denominated in satoshis.
+	pub max_channel_balance_sat: u64,
+	/// The maximum number of blocks after which the channel will be closed automatically.
+	pub max_channel_expiry_blocks: u32,
+}
+
+/// A request for the LSP's advertised options. Carries no parameters.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetInfoRequest {}
+
+/// The LSP's response to a [`GetInfoRequest`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct GetInfoResponse {
+	/// The LSP's website.
+	pub website: String,
+	/// The channel configuration options the LSP supports.
+	pub options: OptionsSupported,
+}
+
+/// A request to create a new channel-purchase order.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateOrderRequest {
+	/// The requested channel parameters.
+	pub order: OrderParams,
+}
+
+/// The LSP's response describing a created (or queried) order.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateOrderResponse {
+	/// The identifier of the order, to be used in subsequent [`GetOrderRequest`]s.
+	pub order_id: OrderId,
+	/// The channel parameters the order was created with.
+	pub order: OrderParams,
+	/// The order's current lifecycle state.
+	pub order_state: OrderState,
+	/// When the order was created.
+	pub created_at: DateTime<Utc>,
+	/// When the order expires if left unpaid.
+	pub expires_at: DateTime<Utc>,
+	/// The payment instructions and bookkeeping for the order.
+	pub payment: OrderPayment,
+	/// The real channel the LSP opened for this order, once known.
+	pub channel: Option<ChannelInfo>,
+}
+
+/// A request for the current status of a previously created order.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct GetOrderRequest {
+	/// The identifier returned by the original [`CreateOrderRequest`].
+	pub order_id: OrderId,
+}
+
+/// An LSPS1 request, as sent by the client.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum LSPS1Request {
+	/// See [`GetInfoRequest`].
+	GetInfo(GetInfoRequest),
+	/// See [`CreateOrderRequest`].
+	CreateOrder(CreateOrderRequest),
+	/// See [`GetOrderRequest`].
+	GetOrder(GetOrderRequest),
+}
+
+/// An LSPS1 response, as sent by the LSP.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum LSPS1Response {
+	/// See [`GetInfoResponse`].
+	GetInfo(GetInfoResponse),
+	/// See [`CreateOrderResponse`].
+	CreateOrder(CreateOrderResponse),
+	/// Returned when a [`CreateOrderRequest`] does not match any option the LSP supports.
+	CreateOrderError(crate::lsps0::ser::ResponseError),
+	/// See [`CreateOrderResponse`]; reused as the response to a [`GetOrderRequest`].
+	GetOrder(CreateOrderResponse),
+}
+
+/// A message in the LSPS1 protocol, tagged with the [`RequestId`] used to correlate a
+/// [`LSPS1Response`] back to the [`LSPS1Request`] that prompted it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum LSPS1Message {
+	/// A request sent by the client.
+	Request(RequestId, LSPS1Request),
+	/// A response sent by the LSP.
+	Response(RequestId, LSPS1Response),
+}
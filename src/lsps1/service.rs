@@ -20,21 +20,30 @@ use crate::message_queue::MessageQueue;
 
 use crate::events::{Event, EventQueue};
 use crate::lsps0::ser::{ProtocolMessageHandler, RequestId, ResponseError};
-use crate::prelude::{HashMap, String, ToString};
+use crate::prelude::{HashMap, String, ToString, Vec};
 use crate::sync::{Arc, Mutex, RwLock};
 use crate::utils;
 
-use lightning::chain::Filter;
+use lightning::chain::transaction::OutPoint;
+use lightning::chain::{Filter, TransactionData, WatchedOutput};
 use lightning::ln::channelmanager::AChannelManager;
-use lightning::ln::msgs::{ErrorAction, LightningError};
+use lightning::ln::types::ChannelId;
+use lightning::ln::msgs::{DecodeError, ErrorAction, LightningError};
+use lightning::offers::offer::{Offer, OfferId};
 use lightning::sign::EntropySource;
 use lightning::util::errors::APIError;
 use lightning::util::logger::Level;
+use lightning::util::ser::{Readable, Writeable, Writer};
+use lightning::io;
 
+use bitcoin::block::Header;
+use bitcoin::hashes::Hash;
 use bitcoin::secp256k1::PublicKey;
+use bitcoin::{Address, ScriptBuf, Txid};
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use core::ops::Deref;
+use core::str::FromStr;
 
 /// Server-side configuration options for LSPS1 channel requests.
 #[derive(Clone, Debug)]
@@ -60,6 +69,7 @@ enum OutboundRequestState {
 	OrderCreated { order_id: OrderId },
 	WaitingPayment { order_id: OrderId },
 	Ready,
+	Expired,
 }
 
 impl OutboundRequestState {
@@ -71,6 +81,14 @@ impl OutboundRequestState {
 			state => Err(ChannelStateError(format!("TODO. JIT Channel was in state: {:?}", state))),
 		}
 	}
+
+	fn payment_received(&self) -> Result<Self, ChannelStateError> {
+		match self {
+			OutboundRequestState::OrderCreated { .. }
+			| OutboundRequestState::WaitingPayment { .. } => Ok(OutboundRequestState::Ready),
+			state => Err(ChannelStateError(format!("TODO. JIT Channel was in state: {:?}", state))),
+		}
+	}
 }
 
 struct OutboundLSPS1Config {
@@ -78,11 +96,38 @@ struct OutboundLSPS1Config {
 	created_at: chrono::DateTime<Utc>,
 	expires_at: chrono::DateTime<Utc>,
 	payment: OrderPayment,
+	/// The BOLT12 offer minted for this order, if the client is paying via a
+	/// reusable offer. Matched back to the order by the offer's own [`OfferId`]
+	/// (`offer.id()`), which the channel manager surfaces on the claimed payment via
+	/// `PaymentPurpose::Bolt12OfferPayment`'s `payment_context` -- unlike a payer-side
+	/// `PaymentId`, this is an identifier the payee (us) actually receives back.
+	offer: Option<Offer>,
+	/// On-chain payment watch registered with the [`Filter`], if the order is
+	/// being paid on-chain. Tracks the watched `scriptPubKey`, the funding
+	/// outpoint once it is seen in a block, and the confirmation bookkeeping
+	/// needed to auto-confirm the payment.
+	watch: Option<OnchainPaymentWatch>,
+}
+
+/// Tracks an on-chain payment towards an order so the handler can count
+/// confirmations itself instead of relying on the operator to poll.
+struct OnchainPaymentWatch {
+	script_pubkey: ScriptBuf,
+	outpoint: Option<OutPoint>,
+	funding_height: Option<u32>,
+	confirmations_required: u32,
 }
 
 struct OutboundCRChannel {
 	state: OutboundRequestState,
 	config: OutboundLSPS1Config,
+	/// The `user_channel_id` the LSP passed to [`ChannelManager::create_channel`]
+	/// when opening the purchased channel, used to link the funded channel back
+	/// to this order for `GetOrder` status reporting.
+	///
+	/// [`ChannelManager::create_channel`]: lightning::ln::channelmanager::ChannelManager::create_channel
+	user_channel_id: Option<u128>,
+	channel_id: Option<ChannelId>,
 }
 
 impl OutboundCRChannel {
@@ -92,7 +137,16 @@ impl OutboundCRChannel {
 	) -> Self {
 		Self {
 			state: OutboundRequestState::OrderCreated { order_id },
-			config: OutboundLSPS1Config { order, created_at, expires_at, payment },
+			config: OutboundLSPS1Config {
+				order,
+				created_at,
+				expires_at,
+				payment,
+				offer: None,
+				watch: None,
+			},
+			user_channel_id: None,
+			channel_id: None,
 		}
 	}
 	fn awaiting_payment(&mut self) -> Result<(), LightningError> {
@@ -100,6 +154,11 @@ impl OutboundCRChannel {
 		Ok(())
 	}
 
+	fn payment_received(&mut self) -> Result<(), LightningError> {
+		self.state = self.state.payment_received()?;
+		Ok(())
+	}
+
 	fn check_order_validity(&self, options_supported: &OptionsSupported) -> bool {
 		let order = &self.config.order;
 
@@ -110,7 +169,6 @@ impl OutboundCRChannel {
 #[derive(Default)]
 struct PeerState {
 	outbound_channels_by_order_id: HashMap<OrderId, OutboundCRChannel>,
-	request_to_cid: HashMap<RequestId, u128>,
 	pending_requests: HashMap<RequestId, LSPS1Request>,
 }
 
@@ -119,15 +177,248 @@ impl PeerState {
 		self.outbound_channels_by_order_id.insert(order_id, channel);
 	}
 
-	fn insert_request(&mut self, request_id: RequestId, channel_id: u128) {
-		self.request_to_cid.insert(request_id, channel_id);
-	}
-
 	fn remove_outbound_channel(&mut self, order_id: OrderId) {
 		self.outbound_channels_by_order_id.remove(&order_id);
 	}
 }
 
+// The `per_peer_state` is serialized so that in-flight orders survive a restart of the LSP. Only
+// the durable order bookkeeping is persisted; transient `pending_requests` are reconstructed empty
+// and rebuilt from freshly received client messages.
+
+impl Writeable for OutboundRequestState {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), io::Error> {
+		match self {
+			OutboundRequestState::OrderCreated { order_id } => {
+				0u8.write(writer)?;
+				order_id.write(writer)?;
+			},
+			OutboundRequestState::WaitingPayment { order_id } => {
+				1u8.write(writer)?;
+				order_id.write(writer)?;
+			},
+			OutboundRequestState::Ready => {
+				2u8.write(writer)?;
+			},
+			OutboundRequestState::Expired => {
+				3u8.write(writer)?;
+			},
+		}
+		Ok(())
+	}
+}
+
+impl Readable for OutboundRequestState {
+	fn read<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		match <u8 as Readable>::read(reader)? {
+			0 => Ok(OutboundRequestState::OrderCreated { order_id: Readable::read(reader)? }),
+			1 => Ok(OutboundRequestState::WaitingPayment { order_id: Readable::read(reader)? }),
+			2 => Ok(OutboundRequestState::Ready),
+			3 => Ok(OutboundRequestState::Expired),
+			_ => Err(DecodeError::InvalidValue),
+		}
+	}
+}
+
+impl Writeable for OnchainPaymentWatch {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), io::Error> {
+		self.script_pubkey.write(writer)?;
+		self.outpoint.write(writer)?;
+		self.funding_height.write(writer)?;
+		self.confirmations_required.write(writer)?;
+		Ok(())
+	}
+}
+
+impl Readable for OnchainPaymentWatch {
+	fn read<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		Ok(OnchainPaymentWatch {
+			script_pubkey: Readable::read(reader)?,
+			outpoint: Readable::read(reader)?,
+			funding_height: Readable::read(reader)?,
+			confirmations_required: Readable::read(reader)?,
+		})
+	}
+}
+
+impl Writeable for OutboundLSPS1Config {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), io::Error> {
+		self.order.write(writer)?;
+		self.created_at.timestamp().write(writer)?;
+		self.expires_at.timestamp().write(writer)?;
+		self.payment.write(writer)?;
+		// Persist the BOLT12 offer as its encoded bytes; its `OfferId` is cheaply
+		// recomputed from the decoded offer on read instead of being stored redundantly.
+		let offer = self.offer.as_ref().map(|offer| offer.encode());
+		offer.write(writer)?;
+		self.watch.write(writer)?;
+		Ok(())
+	}
+}
+
+impl Readable for OutboundLSPS1Config {
+	fn read<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		let order = Readable::read(reader)?;
+		let created_at = read_timestamp(reader)?;
+		let expires_at = read_timestamp(reader)?;
+		let payment = Readable::read(reader)?;
+		let offer_bytes: Option<Vec<u8>> = Readable::read(reader)?;
+		let offer = offer_bytes
+			.map(|bytes| Offer::try_from(bytes).map_err(|_| DecodeError::InvalidValue))
+			.transpose()?;
+		let watch = Readable::read(reader)?;
+		Ok(OutboundLSPS1Config { order, created_at, expires_at, payment, offer, watch })
+	}
+}
+
+fn read_timestamp<R: io::Read>(reader: &mut R) -> Result<DateTime<Utc>, DecodeError> {
+	let secs: i64 = Readable::read(reader)?;
+	DateTime::from_timestamp(secs, 0).ok_or(DecodeError::InvalidValue)
+}
+
+/// Whether a transaction first seen at `funding_height` has `confirmations_required`
+/// confirmations once the chain tip reaches `height`, counting the funding block itself as
+/// the first confirmation.
+fn confirmations_reached(height: u32, funding_height: u32, confirmations_required: u32) -> bool {
+	height.saturating_sub(funding_height) + 1 >= confirmations_required
+}
+
+/// Decodes the per-peer order state written by [`LSPS1ServiceHandler::serialize_state`].
+///
+/// Doesn't depend on `ES`/`CM`/`C` at all, so it's kept as a free function rather than an
+/// associated one: that keeps [`LSPS1ServiceHandler::new`]'s corrupt-state handling testable
+/// without having to construct an [`EntropySource`]/[`AChannelManager`]/[`Filter`] to name the
+/// generic parameters.
+///
+/// [`LSPS1ServiceHandler::serialize_state`]: LSPS1ServiceHandler::serialize_state
+/// [`LSPS1ServiceHandler::new`]: LSPS1ServiceHandler::new
+fn read_per_peer_state<R: io::Read>(
+	reader: &mut R,
+) -> Result<HashMap<PublicKey, Mutex<PeerState>>, DecodeError> {
+	let mut per_peer_state = HashMap::new();
+	let peer_count: u64 = Readable::read(reader)?;
+	for _ in 0..peer_count {
+		let counterparty_node_id: PublicKey = Readable::read(reader)?;
+		let peer_state: PeerState = Readable::read(reader)?;
+		per_peer_state.insert(counterparty_node_id, Mutex::new(peer_state));
+	}
+	Ok(per_peer_state)
+}
+
+/// Links `order_id`'s channel to the real channel the LSP opened, given `order_id` is present
+/// in `peer_state`. Pulled out of [`LSPS1ServiceHandler::link_channel_to_order`] so the linking
+/// logic is testable without a full handler.
+///
+/// [`LSPS1ServiceHandler::link_channel_to_order`]: LSPS1ServiceHandler::link_channel_to_order
+fn link_channel(
+	peer_state: &mut PeerState, order_id: &OrderId, user_channel_id: u128, channel_id: ChannelId,
+) -> Result<(), APIError> {
+	let outbound_channel = peer_state
+		.outbound_channels_by_order_id
+		.get_mut(order_id)
+		.ok_or(APIError::APIMisuseError { err: format!("Channel with order_id {} not found", order_id.0) })?;
+
+	outbound_channel.user_channel_id = Some(user_channel_id);
+	outbound_channel.channel_id = Some(channel_id);
+
+	Ok(())
+}
+
+/// Clears the funding outpoint of any watch in `peer_state` whose current outpoint's txid is
+/// `txid`, returning the scriptPubkeys that need to be re-registered with the [`Filter`]. Pulled
+/// out of [`LSPS1ServiceHandler::transaction_unconfirmed`] so the reorg-handling logic is
+/// testable without a full handler.
+///
+/// [`LSPS1ServiceHandler::transaction_unconfirmed`]: LSPS1ServiceHandler::transaction_unconfirmed
+fn unconfirm_watches_for_txid(peer_state: &mut PeerState, txid: &Txid) -> Vec<ScriptBuf> {
+	let mut to_reregister = Vec::new();
+	for channel in peer_state.outbound_channels_by_order_id.values_mut() {
+		let watch = match channel.config.watch.as_mut() {
+			Some(watch) if watch.outpoint.map(|o| o.txid) == Some(*txid) => watch,
+			_ => continue,
+		};
+
+		watch.outpoint = None;
+		watch.funding_height = None;
+		to_reregister.push(watch.script_pubkey.clone());
+	}
+	to_reregister
+}
+
+/// Sweeps `peer_state` for orders that were created but never paid and have passed their
+/// `expires_at` deadline, marking them `Expired` and removing them. Returns the removed orders'
+/// ids. Pulled out of [`LSPS1ServiceHandler::remove_stale_orders`] so the sweep logic is testable
+/// without a full handler.
+///
+/// [`LSPS1ServiceHandler::remove_stale_orders`]: LSPS1ServiceHandler::remove_stale_orders
+fn sweep_expired_orders(peer_state: &mut PeerState, now: DateTime<Utc>) -> Vec<OrderId> {
+	let mut expired = Vec::new();
+	for (order_id, channel) in peer_state.outbound_channels_by_order_id.iter_mut() {
+		let unpaid = matches!(
+			channel.state,
+			OutboundRequestState::OrderCreated { .. } | OutboundRequestState::WaitingPayment { .. }
+		);
+		if unpaid && channel.config.expires_at < now {
+			channel.state = OutboundRequestState::Expired;
+			expired.push(order_id.clone());
+		}
+	}
+
+	for order_id in &expired {
+		peer_state.outbound_channels_by_order_id.remove(order_id);
+	}
+
+	expired
+}
+
+impl Writeable for OutboundCRChannel {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), io::Error> {
+		self.state.write(writer)?;
+		self.config.write(writer)?;
+		self.user_channel_id.write(writer)?;
+		self.channel_id.write(writer)?;
+		Ok(())
+	}
+}
+
+impl Readable for OutboundCRChannel {
+	fn read<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		Ok(OutboundCRChannel {
+			state: Readable::read(reader)?,
+			config: Readable::read(reader)?,
+			user_channel_id: Readable::read(reader)?,
+			channel_id: Readable::read(reader)?,
+		})
+	}
+}
+
+impl Writeable for PeerState {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), io::Error> {
+		(self.outbound_channels_by_order_id.len() as u64).write(writer)?;
+		for (order_id, channel) in self.outbound_channels_by_order_id.iter() {
+			order_id.write(writer)?;
+			channel.write(writer)?;
+		}
+		Ok(())
+	}
+}
+
+impl Readable for PeerState {
+	fn read<R: io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		let mut outbound_channels_by_order_id = HashMap::new();
+		let channel_count: u64 = Readable::read(reader)?;
+		for _ in 0..channel_count {
+			let order_id: OrderId = Readable::read(reader)?;
+			let channel: OutboundCRChannel = Readable::read(reader)?;
+			outbound_channels_by_order_id.insert(order_id, channel);
+		}
+		Ok(PeerState {
+			outbound_channels_by_order_id,
+			pending_requests: HashMap::new(),
+		})
+	}
+}
+
 /// The main object allowing to send and receive LSPS1 messages.
 pub struct LSPS1ServiceHandler<ES: Deref, CM: Deref + Clone, C: Deref>
 where
@@ -152,19 +443,53 @@ where
 	ES::Target: EntropySource,
 {
 	/// Constructs a `LSPS1ServiceHandler`.
+	///
+	/// If `encoded_state` is set it is expected to be the output of a prior
+	/// [`serialize_state`] call and is used to restore any orders that were
+	/// in-flight before the LSP restarted. A blob that fails to parse is a hard error:
+	/// silently dropping it would discard in-flight orders, which is exactly the data
+	/// loss persistence exists to prevent.
+	///
+	/// [`serialize_state`]: Self::serialize_state
 	pub(crate) fn new(
 		entropy_source: ES, pending_messages: Arc<MessageQueue>, pending_events: Arc<EventQueue>,
 		channel_manager: CM, chain_source: Option<C>, config: LSPS1ServiceConfig,
-	) -> Self {
-		Self {
+		encoded_state: Option<&[u8]>,
+	) -> Result<Self, DecodeError> {
+		let per_peer_state = match encoded_state {
+			Some(mut data) => read_per_peer_state(&mut data)?,
+			None => HashMap::new(),
+		};
+
+		Ok(Self {
 			entropy_source,
 			channel_manager,
 			chain_source,
 			pending_messages,
 			pending_events,
-			per_peer_state: RwLock::new(HashMap::new()),
+			per_peer_state: RwLock::new(per_peer_state),
 			config,
+		})
+	}
+
+	/// Serializes the whole per-peer order state so it can be persisted and
+	/// handed back to [`new`] after a restart.
+	///
+	/// [`new`]: Self::new
+	pub fn serialize_state(&self) -> Vec<u8> {
+		let per_peer_state = self.per_peer_state.read().unwrap();
+		let mut buf = Vec::new();
+		(per_peer_state.len() as u64)
+			.write(&mut buf)
+			.expect("Writing to a Vec is infallible");
+		for (counterparty_node_id, inner_state_lock) in per_peer_state.iter() {
+			let peer_state = inner_state_lock.lock().unwrap();
+			counterparty_node_id
+				.write(&mut buf)
+				.expect("Writing to a Vec is infallible");
+			peer_state.write(&mut buf).expect("Writing to a Vec is infallible");
 		}
+		buf
 	}
 
 	fn handle_get_info_request(
@@ -252,7 +577,7 @@ where
 				match peer_state_lock.pending_requests.remove(&request_id) {
 					Some(LSPS1Request::CreateOrder(params)) => {
 						let order_id = self.generate_order_id();
-						let channel = OutboundCRChannel::new(
+						let channel = self.create_outbound_channel(
 							params.order.clone(),
 							created_at.clone(),
 							expires_at.clone(),
@@ -297,6 +622,132 @@ where
 		Ok(())
 	}
 
+	/// Used by LSP to respond to a [`LSPS1ServiceEvent::RequestForPaymentDetails`] with a BOLT12
+	/// offer minted for the channel purchase price.
+	///
+	/// Since `CM::Target: AChannelManager`, this uses the channel manager's offer builder to mint a
+	/// reusable offer for the `order_total_sat` of `payment`, stashes the offer in the order's
+	/// state, and advertises it to the client through the `bolt12_offer` field of
+	/// [`OrderPayment`]. When the corresponding BOLT12 invoice is requested and paid,
+	/// [`payment_received_for_offer`] matches the inbound payment back to the order by the
+	/// offer's own [`OfferId`] and marks it as paid and ready for the channel to be opened.
+	///
+	/// Should be called in response to receiving a [`LSPS1ServiceEvent::RequestForPaymentDetails`] event.
+	///
+	/// [`LSPS1ServiceEvent::RequestForPaymentDetails`]: crate::lsps1::event::LSPS1ServiceEvent::RequestForPaymentDetails
+	/// [`payment_received_for_offer`]: Self::payment_received_for_offer
+	pub fn send_payment_details_with_offer(
+		&self, request_id: RequestId, counterparty_node_id: &PublicKey, mut payment: OrderPayment,
+		created_at: chrono::DateTime<Utc>, expires_at: chrono::DateTime<Utc>,
+	) -> Result<(), APIError> {
+		let outer_state_lock = self.per_peer_state.read().unwrap();
+
+		match outer_state_lock.get(counterparty_node_id) {
+			Some(inner_state_lock) => {
+				let mut peer_state_lock = inner_state_lock.lock().unwrap();
+
+				match peer_state_lock.pending_requests.remove(&request_id) {
+					Some(LSPS1Request::CreateOrder(params)) => {
+						let offer = self
+							.channel_manager
+							.get_cm()
+							.create_offer_builder(None)
+							.and_then(|builder| {
+								builder.amount_msats(payment.order_total_sat * 1000).build()
+							})
+							.map_err(|e| APIError::APIMisuseError {
+								err: format!("Failed to build BOLT12 offer: {:?}", e),
+							})?;
+
+						payment.bolt12_offer = Some(offer.to_string());
+
+						let order_id = self.generate_order_id();
+						let mut channel = self.create_outbound_channel(
+							params.order.clone(),
+							created_at,
+							expires_at,
+							order_id.clone(),
+							payment.clone(),
+						);
+						channel.config.offer = Some(offer);
+
+						peer_state_lock.insert_outbound_channel(order_id.clone(), channel);
+
+						self.enqueue_response(
+							counterparty_node_id,
+							request_id,
+							LSPS1Response::CreateOrder(CreateOrderResponse {
+								order: params.order,
+								order_id,
+								order_state: OrderState::Created,
+								created_at,
+								expires_at,
+								payment,
+								channel: None,
+							}),
+						);
+					},
+
+					_ => {
+						return Err(APIError::APIMisuseError {
+							err: format!("No pending buy request for request_id: {:?}", request_id),
+						})
+					},
+				}
+			},
+			None => {
+				return Err(APIError::APIMisuseError {
+					err: format!("No state for the counterparty exists: {:?}", counterparty_node_id),
+				})
+			},
+		}
+
+		Ok(())
+	}
+
+	/// Used by LSP to notify the handler that the BOLT12 payment for offer `offer_id` has been
+	/// received, matching it back to the order that minted the corresponding offer and
+	/// marking it as paid and ready for the channel to be opened.
+	///
+	/// Should be called when the channel manager surfaces a claimed payment whose
+	/// `PaymentPurpose::Bolt12OfferPayment` carries the `OfferId` of an offer handed out by
+	/// [`send_payment_details_with_offer`]. Unlike a payer-side `PaymentId`, this is an
+	/// identifier the payee actually gets back from the channel manager on claim.
+	///
+	/// [`send_payment_details_with_offer`]: Self::send_payment_details_with_offer
+	pub fn payment_received_for_offer(
+		&self, counterparty_node_id: &PublicKey, offer_id: OfferId,
+	) -> Result<(), APIError> {
+		let outer_state_lock = self.per_peer_state.read().unwrap();
+
+		match outer_state_lock.get(counterparty_node_id) {
+			Some(inner_state_lock) => {
+				let mut peer_state_lock = inner_state_lock.lock().unwrap();
+
+				let outbound_channel = peer_state_lock
+					.outbound_channels_by_order_id
+					.values_mut()
+					.find(|channel| {
+						channel.config.offer.as_ref().map(|offer| offer.id()) == Some(offer_id)
+					})
+					.ok_or(APIError::APIMisuseError {
+						err: format!("No order awaiting BOLT12 payment for offer {:?}", offer_id),
+					})?;
+
+				outbound_channel.payment_received().map_err(|e| APIError::APIMisuseError {
+					err: format!("Could not mark order as paid: {}", e.err),
+				})?;
+			},
+			None => {
+				return Err(APIError::APIMisuseError {
+					err: format!("No state for the counterparty exists: {:?}", counterparty_node_id),
+				})
+			},
+		}
+
+		Ok(())
+	}
+
 	fn handle_get_order_request(
 		&self, request_id: RequestId, counterparty_node_id: &PublicKey, params: GetOrderRequest,
 	) -> Result<(), LightningError> {
@@ -354,12 +805,18 @@ where
 	/// The LSP continously polls for checking payment confirmation on-chain or lighting
 	/// and then responds to client request.
 	///
+	/// The reported [`ChannelInfo`] is populated from the real channel the LSP opened for
+	/// this order, if [`link_channel_to_order`] has already been called for it and that
+	/// channel's funding outpoint is known; otherwise `channel` is reported as `None`, which is
+	/// expected for a `CheckPaymentConfirmation` raised before any channel has been opened.
+	///
 	/// Should be called in response to receiving a [`LSPS1ServiceEvent::CheckPaymentConfirmation`] event.
 	///
 	/// [`LSPS1ServiceEvent::CheckPaymentConfirmation`]: crate::lsps1::event::LSPS1ServiceEvent::CheckPaymentConfirmation
+	/// [`link_channel_to_order`]: Self::link_channel_to_order
 	pub fn update_order_status(
 		&self, request_id: RequestId, counterparty_node_id: PublicKey, order_id: OrderId,
-		order_state: OrderState, channel: Option<ChannelInfo>,
+		order_state: OrderState,
 	) -> Result<(), APIError> {
 		let outer_state_lock = self.per_peer_state.read().unwrap();
 
@@ -371,6 +828,9 @@ where
 					peer_state_lock.outbound_channels_by_order_id.get_mut(&order_id)
 				{
 					let config = &outbound_channel.config;
+					let channel = outbound_channel
+						.user_channel_id
+						.and_then(|user_channel_id| self.channel_info_for(user_channel_id, config));
 
 					self.enqueue_response(
 						&counterparty_node_id,
@@ -400,6 +860,236 @@ where
 		Ok(())
 	}
 
+	/// Links the channel the LSP opened for `order_id` back to the order.
+	///
+	/// `user_channel_id` and `channel_id` are the identifiers the LSP passed to
+	/// [`ChannelManager::create_channel`] when opening the purchased channel. Once linked,
+	/// [`update_order_status`] populates the reported [`ChannelInfo`] from the real channel's
+	/// funding outpoint instead of values typed in by the operator.
+	///
+	/// Should be called once the LSP has opened the channel for a paid order, separately from
+	/// [`update_order_status`] since a channel need not exist yet when that is called.
+	///
+	/// [`update_order_status`]: Self::update_order_status
+	/// [`ChannelManager::create_channel`]: lightning::ln::channelmanager::ChannelManager::create_channel
+	pub fn link_channel_to_order(
+		&self, counterparty_node_id: PublicKey, order_id: OrderId, user_channel_id: u128,
+		channel_id: ChannelId,
+	) -> Result<(), APIError> {
+		let outer_state_lock = self.per_peer_state.read().unwrap();
+
+		match outer_state_lock.get(&counterparty_node_id) {
+			Some(inner_state_lock) => {
+				let mut peer_state_lock = inner_state_lock.lock().unwrap();
+				link_channel(&mut peer_state_lock, &order_id, user_channel_id, channel_id)
+			},
+			None => Err(APIError::APIMisuseError {
+				err: format!("No existing state with counterparty {}", counterparty_node_id),
+			}),
+		}
+	}
+
+	/// Builds a fresh [`OutboundCRChannel`] for a newly created order and, if the order is
+	/// being paid on-chain, registers the on-chain watch for it. Shared by both
+	/// [`send_payment_details`] and [`send_payment_details_with_offer`] so neither
+	/// order-creation path can forget to register the on-chain payment watch.
+	///
+	/// [`send_payment_details`]: Self::send_payment_details
+	/// [`send_payment_details_with_offer`]: Self::send_payment_details_with_offer
+	fn create_outbound_channel(
+		&self, order: OrderParams, created_at: chrono::DateTime<Utc>,
+		expires_at: chrono::DateTime<Utc>, order_id: OrderId, payment: OrderPayment,
+	) -> OutboundCRChannel {
+		let mut channel = OutboundCRChannel::new(order, created_at, expires_at, order_id, payment);
+		self.register_onchain_payment(&mut channel);
+		channel
+	}
+
+	/// If `channel` is being paid on-chain, registers interest in the payment address with
+	/// the [`Filter`] and records a watch so that [`transactions_confirmed`]/
+	/// [`best_block_updated`] can count confirmations and auto-confirm the order.
+	///
+	/// The paying transaction's txid isn't known yet, so this registers the `scriptPubKey`
+	/// via [`Filter::register_tx`] with a placeholder txid rather than waiting until a
+	/// matching output is already seen in [`transactions_confirmed`]: a real `Filter`
+	/// backed by a compact-filter or Electrum client only fetches/forwards blocks for
+	/// previously-registered txids/outputs, so registering after the fact would never
+	/// see the payment.
+	///
+	/// [`transactions_confirmed`]: Self::transactions_confirmed
+	/// [`best_block_updated`]: Self::best_block_updated
+	fn register_onchain_payment(&self, channel: &mut OutboundCRChannel) {
+		let payment = &channel.config.payment;
+		if let Some(address) = payment.onchain_address.as_ref() {
+			if let Ok(address) = Address::from_str(address) {
+				let script_pubkey = address.assume_checked().script_pubkey();
+
+				if let Some(chain_source) = self.chain_source.as_ref() {
+					chain_source.register_tx(&Txid::all_zeros(), &script_pubkey);
+				}
+
+				channel.config.watch = Some(OnchainPaymentWatch {
+					script_pubkey,
+					outpoint: None,
+					funding_height: None,
+					confirmations_required: payment.minimum_confirmations,
+				});
+			}
+		}
+	}
+
+	/// Notify the handler of transactions confirmed in a block, mirroring
+	/// [`chain::Confirm::transactions_confirmed`].
+	///
+	/// Any output paying one of the watched on-chain payment addresses is
+	/// recorded as the funding outpoint of the corresponding order and, once the
+	/// outpoint is known, registered with the [`Filter`] so later spends are
+	/// surfaced as well.
+	///
+	/// [`chain::Confirm::transactions_confirmed`]: lightning::chain::Confirm::transactions_confirmed
+	pub fn transactions_confirmed(
+		&self, header: &Header, txdata: &TransactionData, height: u32,
+	) {
+		let outer_state_lock = self.per_peer_state.read().unwrap();
+
+		for inner_state_lock in outer_state_lock.values() {
+			let mut peer_state_lock = inner_state_lock.lock().unwrap();
+
+			for channel in peer_state_lock.outbound_channels_by_order_id.values_mut() {
+				let watch = match channel.config.watch.as_mut() {
+					Some(watch) if watch.outpoint.is_none() => watch,
+					_ => continue,
+				};
+
+				for (_, tx) in txdata {
+					for (index, output) in tx.output.iter().enumerate() {
+						if output.script_pubkey == watch.script_pubkey {
+							let outpoint = OutPoint { txid: tx.compute_txid(), index: index as u16 };
+							watch.outpoint = Some(outpoint);
+							watch.funding_height = Some(height);
+
+							if let Some(chain_source) = self.chain_source.as_ref() {
+								chain_source.register_output(WatchedOutput {
+									block_hash: Some(header.block_hash()),
+									outpoint,
+									script_pubkey: watch.script_pubkey.clone(),
+								});
+							}
+						}
+					}
+				}
+			}
+		}
+	}
+
+	/// Notify the handler that the previously-confirmed transaction `txid` has been
+	/// reorged out of the best chain, mirroring [`chain::Confirm::transaction_unconfirmed`].
+	///
+	/// Any watch whose funding outpoint came from `txid` is unwound so [`best_block_updated`]
+	/// stops counting confirmations for a transaction that no longer exists, and interest in
+	/// the payment `scriptPubKey` is re-registered with the [`Filter`] so a replacement
+	/// payment is not missed.
+	///
+	/// [`chain::Confirm::transaction_unconfirmed`]: lightning::chain::Confirm::transaction_unconfirmed
+	/// [`best_block_updated`]: Self::best_block_updated
+	pub fn transaction_unconfirmed(&self, txid: &Txid) {
+		let outer_state_lock = self.per_peer_state.read().unwrap();
+
+		for inner_state_lock in outer_state_lock.values() {
+			let mut peer_state_lock = inner_state_lock.lock().unwrap();
+
+			for script_pubkey in unconfirm_watches_for_txid(&mut peer_state_lock, txid) {
+				if let Some(chain_source) = self.chain_source.as_ref() {
+					chain_source.register_tx(&Txid::all_zeros(), &script_pubkey);
+				}
+			}
+		}
+	}
+
+	/// Notify the handler that the chain tip advanced to `height`, mirroring
+	/// [`chain::Confirm::best_block_updated`].
+	///
+	/// Any on-chain order payment that has reached its required number of confirmations is
+	/// transitioned towards readiness, an [`OrderPaymentConfirmed`] event is enqueued so the
+	/// channel open can proceed, and the now-redundant watch is pruned. This is triggered by
+	/// chain activity, not a client request, so it deliberately does not reuse
+	/// [`CheckPaymentConfirmation`]: that event's `request_id` is replied to over the wire, and
+	/// there is no client request here to correlate a reply to. Funding transactions that have
+	/// been reorged out are excluded by [`transaction_unconfirmed`], which clears their
+	/// `funding_height` so they are no longer counted here.
+	///
+	/// [`chain::Confirm::best_block_updated`]: lightning::chain::Confirm::best_block_updated
+	/// [`OrderPaymentConfirmed`]: crate::lsps1::event::LSPS1ServiceEvent::OrderPaymentConfirmed
+	/// [`CheckPaymentConfirmation`]: crate::lsps1::event::LSPS1ServiceEvent::CheckPaymentConfirmation
+	/// [`transaction_unconfirmed`]: Self::transaction_unconfirmed
+	pub fn best_block_updated(&self, _header: &Header, height: u32) {
+		let outer_state_lock = self.per_peer_state.read().unwrap();
+
+		for (counterparty_node_id, inner_state_lock) in outer_state_lock.iter() {
+			let mut peer_state_lock = inner_state_lock.lock().unwrap();
+
+			let mut confirmed = Vec::new();
+			for (order_id, channel) in peer_state_lock.outbound_channels_by_order_id.iter_mut() {
+				match channel.config.watch.as_ref() {
+					Some(OnchainPaymentWatch {
+						funding_height: Some(funding_height),
+						confirmations_required,
+						..
+					}) => {
+						if !confirmations_reached(height, *funding_height, *confirmations_required) {
+							continue;
+						}
+					},
+					_ => continue,
+				}
+
+				if channel.payment_received().is_ok() {
+					channel.config.watch = None;
+					confirmed.push(order_id.clone());
+				}
+			}
+
+			for order_id in confirmed {
+				self.pending_events.enqueue(Event::LSPS1Service(
+					LSPS1ServiceEvent::OrderPaymentConfirmed {
+						counterparty_node_id: *counterparty_node_id,
+						order_id,
+					},
+				));
+			}
+		}
+	}
+
+	/// Sweeps out orders that were created but never paid and have passed their
+	/// `expires_at` deadline.
+	///
+	/// For every [`OutboundCRChannel`] still in `OrderCreated`/`WaitingPayment`
+	/// past its expiry, the order is marked `Expired`, removed from the peer's
+	/// state, and an [`LSPS1ServiceEvent::OrderExpired`] event is enqueued so the LSP
+	/// can return any partial on-chain payment it received. Intended to be
+	/// called periodically from the background processor.
+	///
+	/// This sweep is triggered by the clock, not a client request, so it deliberately does not
+	/// reuse [`LSPS1ServiceEvent::Refund`]: that event's `request_id` is replied to over the
+	/// wire, and there is no client request here to correlate a reply to.
+	///
+	/// [`LSPS1ServiceEvent::OrderExpired`]: crate::lsps1::event::LSPS1ServiceEvent::OrderExpired
+	/// [`LSPS1ServiceEvent::Refund`]: crate::lsps1::event::LSPS1ServiceEvent::Refund
+	pub fn remove_stale_orders(&self, now: DateTime<Utc>) {
+		let outer_state_lock = self.per_peer_state.read().unwrap();
+
+		for (counterparty_node_id, inner_state_lock) in outer_state_lock.iter() {
+			let mut peer_state_lock = inner_state_lock.lock().unwrap();
+
+			for order_id in sweep_expired_orders(&mut peer_state_lock, now) {
+				self.pending_events.enqueue(Event::LSPS1Service(LSPS1ServiceEvent::OrderExpired {
+					counterparty_node_id: *counterparty_node_id,
+					order_id,
+				}));
+			}
+		}
+	}
+
 	fn enqueue_response(
 		&self, counterparty_node_id: &PublicKey, request_id: RequestId, response: LSPS1Response,
 	) {
@@ -407,6 +1097,27 @@ where
 			.enqueue(counterparty_node_id, LSPS1Message::Response(request_id, response).into());
 	}
 
+	/// Builds the [`ChannelInfo`] reported in a `GetOrder` response from the real channel the LSP
+	/// opened, located by its `user_channel_id`. Returns `None` until the channel's funding
+	/// transaction outpoint is known.
+	fn channel_info_for(
+		&self, user_channel_id: u128, config: &OutboundLSPS1Config,
+	) -> Option<ChannelInfo> {
+		let funding_outpoint = self
+			.channel_manager
+			.get_cm()
+			.list_channels()
+			.into_iter()
+			.find(|details| details.user_channel_id == user_channel_id)
+			.and_then(|details| details.funding_txo)?;
+
+		Some(ChannelInfo {
+			funded_at: config.created_at,
+			funding_outpoint,
+			expires_at: config.expires_at,
+		})
+	}
+
 	fn generate_order_id(&self) -> OrderId {
 		let bytes = self.entropy_source.get_secure_random_bytes();
 		OrderId(utils::hex_str(&bytes[0..16]))
@@ -448,3 +1159,243 @@ where
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use lightning::offers::offer::OfferBuilder;
+
+	use bitcoin::secp256k1::{Secp256k1, SecretKey};
+
+	fn sample_order_params() -> OrderParams {
+		OrderParams {
+			lsp_balance_sat: 1_000_000,
+			client_balance_sat: 0,
+			required_channel_confirmations: 3,
+			funding_confirms_within_blocks: 6,
+			channel_expiry_blocks: 13_000,
+			token: None,
+			refund_onchain_address: None,
+			announce_channel: false,
+		}
+	}
+
+	fn sample_order_payment() -> OrderPayment {
+		OrderPayment {
+			order_total_sat: 100_000,
+			fee_total_sat: 1_000,
+			bolt11_invoice: None,
+			onchain_address: Some("bcrt1qexampleaddress".to_string()),
+			minimum_confirmations: 3,
+			bolt12_offer: None,
+		}
+	}
+
+	fn sample_offer() -> Offer {
+		let secp_ctx = Secp256k1::new();
+		let secret_key = SecretKey::from_slice(&[0x42; 32]).unwrap();
+		let signing_pubkey = bitcoin::secp256k1::PublicKey::from_secret_key(&secp_ctx, &secret_key);
+		OfferBuilder::new(signing_pubkey).amount_msats(100_000_000).build().unwrap()
+	}
+
+	#[test]
+	fn peer_state_round_trips_through_writeable_with_nested_offer_and_watch() {
+		let order_id = OrderId("order-1".to_string());
+		let created_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+		let expires_at = DateTime::from_timestamp(1_700_100_000, 0).unwrap();
+
+		let mut channel = OutboundCRChannel::new(
+			sample_order_params(),
+			created_at,
+			expires_at,
+			order_id.clone(),
+			sample_order_payment(),
+		);
+		let offer = sample_offer();
+		channel.config.offer = Some(offer.clone());
+		channel.config.watch = Some(OnchainPaymentWatch {
+			script_pubkey: ScriptBuf::from(vec![0x00, 0x14]),
+			outpoint: Some(OutPoint { txid: Txid::all_zeros(), index: 0 }),
+			funding_height: Some(100),
+			confirmations_required: 3,
+		});
+		channel.user_channel_id = Some(42);
+		channel.channel_id = Some(ChannelId([7u8; 32]));
+
+		let mut peer_state = PeerState::default();
+		peer_state.insert_outbound_channel(order_id.clone(), channel);
+
+		let mut buf = Vec::new();
+		peer_state.write(&mut buf).unwrap();
+		let deserialized: PeerState = Readable::read(&mut &buf[..]).unwrap();
+
+		let restored = deserialized.outbound_channels_by_order_id.get(&order_id).unwrap();
+		assert_eq!(restored.state, OutboundRequestState::OrderCreated { order_id: order_id.clone() });
+		assert_eq!(restored.user_channel_id, Some(42));
+		assert_eq!(restored.channel_id, Some(ChannelId([7u8; 32])));
+		assert_eq!(restored.config.payment.order_total_sat, 100_000);
+		assert_eq!(restored.config.order.lsp_balance_sat, 1_000_000);
+		assert_eq!(restored.config.offer.as_ref().map(|o| o.id()), Some(offer.id()));
+		assert!(restored.config.watch.is_some());
+	}
+
+	#[test]
+	fn new_rejects_corrupt_encoded_state() {
+		// A peer count that promises more entries than are actually present.
+		let mut corrupt = Vec::new();
+		5u64.write(&mut corrupt).unwrap();
+
+		let result = read_per_peer_state(&mut &corrupt[..]);
+		assert!(result.is_err());
+	}
+
+	#[test]
+	fn sweep_expired_orders_removes_unpaid_past_expiry_and_leaves_others() {
+		let now = DateTime::from_timestamp(1_700_100_000, 0).unwrap();
+		let created_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+		let past_expiry = DateTime::from_timestamp(1_700_050_000, 0).unwrap();
+		let future_expiry = DateTime::from_timestamp(1_700_200_000, 0).unwrap();
+
+		let expired_id = OrderId("expired".to_string());
+		let mut expired_channel = OutboundCRChannel::new(
+			sample_order_params(),
+			created_at,
+			past_expiry,
+			expired_id.clone(),
+			sample_order_payment(),
+		);
+		expired_channel.awaiting_payment().unwrap();
+
+		let live_id = OrderId("live".to_string());
+		let live_channel = OutboundCRChannel::new(
+			sample_order_params(),
+			created_at,
+			future_expiry,
+			live_id.clone(),
+			sample_order_payment(),
+		);
+
+		let mut peer_state = PeerState::default();
+		peer_state.insert_outbound_channel(expired_id.clone(), expired_channel);
+		peer_state.insert_outbound_channel(live_id.clone(), live_channel);
+
+		let removed = sweep_expired_orders(&mut peer_state, now);
+
+		assert_eq!(removed, vec![expired_id.clone()]);
+		assert!(!peer_state.outbound_channels_by_order_id.contains_key(&expired_id));
+		assert!(peer_state.outbound_channels_by_order_id.contains_key(&live_id));
+	}
+
+	#[test]
+	fn link_channel_populates_ids_for_known_order() {
+		let order_id = OrderId("order-1".to_string());
+		let created_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+		let expires_at = DateTime::from_timestamp(1_700_100_000, 0).unwrap();
+		let channel = OutboundCRChannel::new(
+			sample_order_params(),
+			created_at,
+			expires_at,
+			order_id.clone(),
+			sample_order_payment(),
+		);
+
+		let mut peer_state = PeerState::default();
+		peer_state.insert_outbound_channel(order_id.clone(), channel);
+
+		link_channel(&mut peer_state, &order_id, 7, ChannelId([1u8; 32])).unwrap();
+
+		let linked = peer_state.outbound_channels_by_order_id.get(&order_id).unwrap();
+		assert_eq!(linked.user_channel_id, Some(7));
+		assert_eq!(linked.channel_id, Some(ChannelId([1u8; 32])));
+	}
+
+	#[test]
+	fn link_channel_errors_for_unknown_order() {
+		let mut peer_state = PeerState::default();
+		let result =
+			link_channel(&mut peer_state, &OrderId("missing".to_string()), 7, ChannelId([1u8; 32]));
+		assert!(result.is_err());
+	}
+
+	#[test]
+	fn unconfirm_watches_for_txid_clears_matching_outpoint() {
+		let order_id = OrderId("order-1".to_string());
+		let created_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+		let expires_at = DateTime::from_timestamp(1_700_100_000, 0).unwrap();
+		let mut channel = OutboundCRChannel::new(
+			sample_order_params(),
+			created_at,
+			expires_at,
+			order_id.clone(),
+			sample_order_payment(),
+		);
+		let reorged_txid = Txid::all_zeros();
+		let script_pubkey = ScriptBuf::from(vec![0x00, 0x14]);
+		channel.config.watch = Some(OnchainPaymentWatch {
+			script_pubkey: script_pubkey.clone(),
+			outpoint: Some(OutPoint { txid: reorged_txid, index: 0 }),
+			funding_height: Some(100),
+			confirmations_required: 3,
+		});
+
+		let mut peer_state = PeerState::default();
+		peer_state.insert_outbound_channel(order_id.clone(), channel);
+
+		let to_reregister = unconfirm_watches_for_txid(&mut peer_state, &reorged_txid);
+
+		assert_eq!(to_reregister, vec![script_pubkey]);
+		let watch =
+			peer_state.outbound_channels_by_order_id.get(&order_id).unwrap().config.watch.as_ref().unwrap();
+		assert!(watch.outpoint.is_none());
+		assert!(watch.funding_height.is_none());
+	}
+
+	#[test]
+	fn onchain_payment_watch_round_trips_through_writeable() {
+		let script_pubkey = ScriptBuf::from(vec![0x00, 0x14]);
+		let outpoint = OutPoint { txid: Txid::all_zeros(), index: 3 };
+		let watch = OnchainPaymentWatch {
+			script_pubkey: script_pubkey.clone(),
+			outpoint: Some(outpoint),
+			funding_height: Some(42),
+			confirmations_required: 6,
+		};
+
+		let mut buf = Vec::new();
+		watch.write(&mut buf).unwrap();
+		let deserialized: OnchainPaymentWatch = Readable::read(&mut &buf[..]).unwrap();
+
+		assert_eq!(deserialized.script_pubkey, script_pubkey);
+		assert_eq!(deserialized.outpoint, Some(outpoint));
+		assert_eq!(deserialized.funding_height, Some(42));
+		assert_eq!(deserialized.confirmations_required, 6);
+	}
+
+	#[test]
+	fn outbound_request_state_round_trips_through_writeable() {
+		let order_id = OrderId("deadbeef".to_string());
+		let states = [
+			OutboundRequestState::OrderCreated { order_id: order_id.clone() },
+			OutboundRequestState::WaitingPayment { order_id },
+			OutboundRequestState::Ready,
+			OutboundRequestState::Expired,
+		];
+
+		for state in states {
+			let mut buf = Vec::new();
+			state.write(&mut buf).unwrap();
+			let deserialized: OutboundRequestState = Readable::read(&mut &buf[..]).unwrap();
+			assert_eq!(deserialized, state);
+		}
+	}
+
+	#[test]
+	fn confirmations_reached_counts_the_funding_block_itself() {
+		// The funding tx's own block counts as the first confirmation.
+		assert!(!confirmations_reached(100, 100, 2));
+		assert!(confirmations_reached(101, 100, 2));
+		assert!(confirmations_reached(105, 100, 2));
+		assert!(confirmations_reached(100, 100, 1));
+	}
+}